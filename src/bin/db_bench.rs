@@ -0,0 +1,246 @@
+use std::env;
+use std::process;
+use std::sync::{mpsc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use rand::random;
+
+use rust_kvs::data::memtable::{MemTable, DEFAULT_MAX_KEY_SIZE, DEFAULT_MAX_VALUE_SIZE};
+
+const USAGE: &str = "usage: db_bench [--benchmarks=fillseq,fillrandom,overwrite,readrandom,readseq] \
+[--num=N] [--key_size=N] [--value_size=N] [--threads=N]";
+
+const BENCHMARKS: [&str; 5] = ["fillseq", "fillrandom", "overwrite", "readrandom", "readseq"];
+
+struct Config {
+    benchmarks: Vec<String>,
+    num: usize,
+    key_size: usize,
+    value_size: usize,
+    threads: usize,
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn parse_number(name: &str, value: &str) -> usize {
+    value.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid value for {}: {}", name, value)))
+}
+
+fn parse_args() -> Config {
+    let mut config = Config {
+        benchmarks: vec!["fillseq", "fillrandom", "overwrite", "readrandom"]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        num: 100_000,
+        key_size: 16,
+        value_size: 100,
+        threads: 1,
+    };
+    for arg in env::args().skip(1) {
+        let (name, value) = arg
+            .split_once('=')
+            .unwrap_or_else(|| exit_with_usage(&format!("invalid flag: {}", arg)));
+        match name {
+            "--benchmarks" => config.benchmarks = value.split(',').map(String::from).collect(),
+            "--num" => config.num = parse_number(name, value),
+            "--key_size" => config.key_size = parse_number(name, value),
+            "--value_size" => config.value_size = parse_number(name, value),
+            "--threads" => config.threads = parse_number(name, value),
+            _ => exit_with_usage(&format!("unknown flag: {}", name)),
+        }
+    }
+    if let Some(name) = config.benchmarks.iter().find(|name| !BENCHMARKS.contains(&name.as_str())) {
+        exit_with_usage(&format!("unknown benchmark: {}", name));
+    }
+    if config.key_size == 0 || config.key_size > DEFAULT_MAX_KEY_SIZE {
+        exit_with_usage(&format!("--key_size must be between 1 and {}", DEFAULT_MAX_KEY_SIZE));
+    }
+    if config.value_size > DEFAULT_MAX_VALUE_SIZE {
+        exit_with_usage(&format!("--value_size must be at most {}", DEFAULT_MAX_VALUE_SIZE));
+    }
+    if config.threads == 0 {
+        exit_with_usage("--threads must be positive");
+    }
+    config
+}
+
+struct Histogram {
+    samples: Vec<Duration>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Histogram { samples: Vec::new() }
+    }
+
+    fn add(&mut self, latency: Duration) {
+        self.samples.push(latency);
+    }
+
+    fn merge(&mut self, other: Histogram) {
+        self.samples.extend(other.samples);
+    }
+
+    // Latencies fall into buckets of powers of two microseconds, the first one
+    // holding those below a microsecond.
+    fn bucket_counts(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        for sample in &self.samples {
+            let bucket = (u128::BITS - sample.as_micros().leading_zeros()) as usize;
+            if counts.len() <= bucket {
+                counts.resize(bucket + 1, 0);
+            }
+            counts[bucket] += 1;
+        }
+        counts
+    }
+
+    fn percentile(&self, p: f64) -> Duration {
+        let index = ((self.samples.len() - 1) as f64 * p).round() as usize;
+        self.samples[index]
+    }
+
+    fn report(&mut self, name: &str, elapsed: Duration, message: &str) {
+        if self.samples.is_empty() {
+            println!("{:<12}: no operations", name);
+            return;
+        }
+        self.samples.sort();
+        let micros_per_op = elapsed.as_secs_f64() * 1e6 / self.samples.len() as f64;
+        println!(
+            "{:<12}: {:>10.3} micros/op {:>12.0} ops/sec; P50: {:?} P99: {:?} Max: {:?} {}",
+            name,
+            micros_per_op,
+            self.samples.len() as f64 / elapsed.as_secs_f64(),
+            self.percentile(0.5),
+            self.percentile(0.99),
+            self.samples[self.samples.len() - 1],
+            message,
+        );
+        let mut cumulative = 0;
+        for (bucket, count) in self.bucket_counts().into_iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            cumulative += count;
+            let lower = if bucket == 0 { 0 } else { 1u128 << (bucket - 1) };
+            println!(
+                "  [{:>10}, {:>10}) us {:>10} {:>8.3}% {:>8.3}%",
+                lower,
+                1u128 << bucket,
+                count,
+                count as f64 * 100.0 / self.samples.len() as f64,
+                cumulative as f64 * 100.0 / self.samples.len() as f64,
+            );
+        }
+    }
+}
+
+// Zero-pads the number to the key size, keeping only the trailing digits when it is longer.
+fn make_key(i: usize, key_size: usize) -> Bytes {
+    let digits = i.to_string();
+    let mut key = vec![b'0'; key_size.saturating_sub(digits.len())];
+    key.extend_from_slice(&digits.as_bytes()[digits.len().saturating_sub(key_size)..]);
+    Bytes::from(key)
+}
+
+fn read_seq(config: &Config, memtable: &MemTable, histogram: &mut Histogram) -> usize {
+    let mut iter = memtable.iter();
+    let mut found = 0;
+    for _ in 0..config.num {
+        let op_start = Instant::now();
+        if iter.next().is_none() {
+            break;
+        }
+        histogram.add(op_start.elapsed());
+        found += 1;
+    }
+    found
+}
+
+// Runs the benchmark on the memtable of the calling thread.
+fn run(name: &str, config: &Config, memtable: &mut MemTable) -> (Histogram, usize) {
+    let value = Bytes::from(vec![b'x'; config.value_size]);
+    let mut histogram = Histogram::new();
+    let mut found = 0;
+    if name.starts_with("fill") {
+        *memtable = MemTable::new();
+    }
+    if name == "readseq" {
+        found = read_seq(config, memtable, &mut histogram);
+    } else {
+        for i in 0..config.num {
+            let op_start = Instant::now();
+            match name {
+                "fillseq" => {
+                    memtable.put(make_key(i, config.key_size), value.clone()).unwrap();
+                }
+                "fillrandom" | "overwrite" => {
+                    let key = make_key(random::<usize>() % config.num, config.key_size);
+                    memtable.put(key, value.clone()).unwrap();
+                }
+                "readrandom" => {
+                    let key = make_key(random::<usize>() % config.num, config.key_size);
+                    if memtable.get(&key).is_some() {
+                        found += 1;
+                    }
+                }
+                _ => unreachable!("benchmark names are checked in parse_args"),
+            }
+            histogram.add(op_start.elapsed());
+        }
+    }
+    (histogram, found)
+}
+
+// MemTable is not Send, so every thread fills and reads a memtable of its own.
+// The threads start each benchmark together, and the main thread merges their
+// results into a single report.
+fn main() {
+    let config = parse_args();
+    println!("Keys:    {} bytes each", config.key_size);
+    println!("Values:  {} bytes each", config.value_size);
+    println!("Entries: {}", config.num);
+    println!("Threads: {}", config.threads);
+    println!("------------------------------------------------");
+    let barrier = Barrier::new(config.threads);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..config.threads {
+            let sender = sender.clone();
+            let (config, barrier) = (&config, &barrier);
+            scope.spawn(move || {
+                let mut memtable = MemTable::new();
+                for name in &config.benchmarks {
+                    barrier.wait();
+                    let start = Instant::now();
+                    let (histogram, found) = run(name, config, &mut memtable);
+                    sender.send((histogram, found, start.elapsed())).unwrap();
+                }
+            });
+        }
+        // Results of a benchmark all arrive before any of the next one, since
+        // no thread starts the next one until every thread has sent its results.
+        for name in &config.benchmarks {
+            let mut histogram = Histogram::new();
+            let mut found = 0;
+            let mut elapsed = Duration::ZERO;
+            for (thread_histogram, thread_found, thread_elapsed) in receiver.iter().take(config.threads) {
+                histogram.merge(thread_histogram);
+                found += thread_found;
+                elapsed = elapsed.max(thread_elapsed);
+            }
+            let message = match name.as_str() {
+                "readrandom" | "readseq" => format!("({} of {} found)", found, config.num * config.threads),
+                _ => String::new(),
+            };
+            histogram.report(name, elapsed, &message);
+        }
+    });
+}
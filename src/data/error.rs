@@ -0,0 +1,20 @@
+use alloc::string::String;
+use core::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidArgument(String),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidArgument(message) => write!(f, "invalid argument: {}", message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
@@ -0,0 +1,99 @@
+use alloc::vec::Vec;
+use core::ops::Bound;
+
+use bytes::Bytes;
+
+use crate::data::internal_key::SequenceNumber;
+use crate::data::memtable::MemTableIterator;
+
+const CRC32_POLYNOMIAL: u32 = 0xedb88320;
+
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32_POLYNOMIAL } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+// CRC-32 of the sequence number and the length-prefixed keys and values, all little endian.
+fn checksum(sequence: SequenceNumber, entries: &[(Bytes, Bytes)]) -> u32 {
+    let mut crc = crc32_update(!0, &sequence.to_le_bytes());
+    for (key, value) in entries {
+        crc = crc32_update(crc, &(key.len() as u64).to_le_bytes());
+        crc = crc32_update(crc, key);
+        crc = crc32_update(crc, &(value.len() as u64).to_le_bytes());
+        crc = crc32_update(crc, value);
+    }
+    !crc
+}
+
+// Entries of an export at a fixed sequence number. The sequence number and the
+// last key are enough to resume the export right after the chunk.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExportChunk {
+    pub sequence: SequenceNumber,
+    pub entries: Vec<(Bytes, Bytes)>,
+    pub last_key: Bytes,
+    pub checksum: u32,
+}
+
+impl ExportChunk {
+    pub fn verify(&self) -> bool {
+        let last_key_matches = self.entries.last().map(|(key, _)| key) == Some(&self.last_key);
+        last_key_matches && self.checksum == checksum(self.sequence, &self.entries)
+    }
+
+    // Start bound of the range to export next.
+    pub fn resume_after(&self) -> Bound<Bytes> {
+        Bound::Excluded(self.last_key.clone())
+    }
+}
+
+pub struct ExportStream<'a> {
+    iter: MemTableIterator<'a>,
+    sequence: SequenceNumber,
+    chunk_size: usize,
+}
+
+impl<'a> ExportStream<'a> {
+    pub fn new(iter: MemTableIterator<'a>, sequence: SequenceNumber, chunk_size: usize) -> Self {
+        ExportStream {
+            iter,
+            sequence,
+            chunk_size,
+        }
+    }
+}
+
+impl Iterator for ExportStream<'_> {
+    type Item = ExportChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entries = self.iter.next_batch(self.chunk_size);
+        let last_key = entries.last()?.0.clone();
+        Some(ExportChunk {
+            sequence: self.sequence,
+            checksum: checksum(self.sequence, &entries),
+            entries,
+            last_key,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::data::export::checksum;
+
+    #[test]
+    fn checksum_is_crc32() {
+        // Without entries only the eight zero bytes of the sequence number are checksummed.
+        let entries = [(Bytes::new(), Bytes::new())];
+        assert_eq!(checksum(0, &[]), 0x6522df69);
+        assert_ne!(checksum(0, &entries), checksum(1, &entries));
+    }
+}
@@ -0,0 +1,79 @@
+use core::cmp::Ordering;
+
+use bytes::Bytes;
+
+pub type SequenceNumber = u64;
+
+pub const MAX_SEQUENCE_NUMBER: SequenceNumber = u64::MAX;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValueType {
+    Deletion,
+    #[default]
+    Value,
+    Merge,
+}
+
+// Sorts before every other entry with the same user key and sequence number.
+pub const VALUE_TYPE_FOR_SEEK: ValueType = ValueType::Merge;
+
+// Entries for the same user key are ordered by decreasing sequence number,
+// so that the newest version is found first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InternalKey {
+    pub user_key: Bytes,
+    pub sequence: SequenceNumber,
+    pub value_type: ValueType,
+}
+
+impl InternalKey {
+    pub fn new(user_key: Bytes, sequence: SequenceNumber, value_type: ValueType) -> Self {
+        InternalKey {
+            user_key,
+            sequence,
+            value_type,
+        }
+    }
+}
+
+impl InternalKey {
+    // Sorts before every entry of the user key.
+    pub fn first_of(user_key: Bytes) -> Self {
+        InternalKey::new(user_key, MAX_SEQUENCE_NUMBER, VALUE_TYPE_FOR_SEEK)
+    }
+
+    // Sorts after every entry of the user key, since sequence numbers start from 1.
+    pub fn last_of(user_key: Bytes) -> Self {
+        InternalKey::new(user_key, 0, ValueType::Deletion)
+    }
+}
+
+impl Ord for InternalKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.user_key.cmp(&other.user_key)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+            .then_with(|| other.value_type.cmp(&self.value_type))
+    }
+}
+
+impl PartialOrd for InternalKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::data::internal_key::{InternalKey, ValueType};
+
+    #[test]
+    fn newer_sequence_comes_first() {
+        let older = InternalKey::new(Bytes::from("a"), 1, ValueType::Value);
+        let newer = InternalKey::new(Bytes::from("a"), 2, ValueType::Value);
+        let other = InternalKey::new(Bytes::from("b"), 3, ValueType::Value);
+        assert!(newer < older);
+        assert!(older < other);
+    }
+}
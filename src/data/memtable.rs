@@ -0,0 +1,1000 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Bound, RangeBounds};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+
+use crate::data::error::{Error, Result};
+use crate::data::export::ExportStream;
+use crate::data::internal_key::{InternalKey, SequenceNumber, ValueType, VALUE_TYPE_FOR_SEEK};
+use crate::data::merge_operator::MergeOperator;
+use crate::data::range_tombstone::RangeTombstones;
+use crate::data::skiplist::SkipList;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LookupResult {
+    Found(Bytes),
+    Deleted,
+    NotFound,
+}
+
+#[derive(Clone, Default)]
+struct TableValue {
+    value: Bytes,
+    // Milliseconds since the unix epoch
+    expire_at: Option<u64>,
+}
+
+impl TableValue {
+    // The clock is only read for entries with a TTL.
+    fn is_expired(&self) -> bool {
+        matches!(self.expire_at, Some(expire_at) if expire_at <= now_millis())
+    }
+}
+
+pub const DEFAULT_MAX_KEY_SIZE: usize = 8 << 20;
+pub const DEFAULT_MAX_VALUE_SIZE: usize = 1 << 30;
+
+#[cfg(feature = "std")]
+fn now_millis() -> u64 {
+    // A clock set before the epoch reads as the epoch itself.
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+}
+
+// TTLs can only be set with a clock, so without std nothing ever expires.
+#[cfg(not(feature = "std"))]
+fn now_millis() -> u64 {
+    0
+}
+
+pub struct MemTable {
+    table: SkipList<InternalKey, TableValue>,
+    range_tombstones: RangeTombstones,
+    last_sequence: SequenceNumber,
+    num_entries: usize,
+    merge_operator: Option<Box<dyn MergeOperator>>,
+    default_ttl: Option<Duration>,
+    max_key_size: usize,
+    max_value_size: usize,
+}
+
+impl Default for MemTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemTable {
+    pub fn new() -> Self {
+        MemTable {
+            table: SkipList::new(),
+            range_tombstones: RangeTombstones::new(),
+            last_sequence: 0,
+            num_entries: 0,
+            merge_operator: None,
+            default_ttl: None,
+            max_key_size: DEFAULT_MAX_KEY_SIZE,
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+        }
+    }
+
+    pub fn with_merge_operator(merge_operator: Box<dyn MergeOperator>) -> Self {
+        MemTable {
+            merge_operator: Some(merge_operator),
+            ..Self::new()
+        }
+    }
+
+    pub fn last_sequence(&self) -> SequenceNumber {
+        self.last_sequence
+    }
+
+    #[cfg(feature = "std")]
+    pub fn set_default_ttl(&mut self, ttl: Option<Duration>) {
+        self.default_ttl = ttl;
+    }
+
+    pub fn set_max_key_size(&mut self, max_key_size: usize) {
+        self.max_key_size = max_key_size;
+    }
+
+    pub fn set_max_value_size(&mut self, max_value_size: usize) {
+        self.max_value_size = max_value_size;
+    }
+
+    fn check_key_size(&self, key: &Bytes) -> Result<()> {
+        if key.len() > self.max_key_size {
+            return Err(Error::InvalidArgument(format!(
+                "key size {} exceeds the limit of {} bytes", key.len(), self.max_key_size
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_value_size(&self, value: &Bytes) -> Result<()> {
+        if value.len() > self.max_value_size {
+            return Err(Error::InvalidArgument(format!(
+                "value size {} exceeds the limit of {} bytes", value.len(), self.max_value_size
+            )));
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, value_type: ValueType, key: Bytes, value: Bytes, ttl: Option<Duration>) -> Result<SequenceNumber> {
+        self.check_key_size(&key)?;
+        self.check_value_size(&value)?;
+        self.last_sequence += 1;
+        let internal_key = InternalKey::new(key, self.last_sequence, value_type);
+        let expire_at = ttl.map(|ttl| {
+            let ttl_millis = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+            now_millis().saturating_add(ttl_millis)
+        });
+        self.table.insert(internal_key, TableValue { value, expire_at });
+        self.num_entries += 1;
+        Ok(self.last_sequence)
+    }
+
+    pub fn put(&mut self, key: Bytes, value: Bytes) -> Result<SequenceNumber> {
+        self.add(ValueType::Value, key, value, self.default_ttl)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn put_with_ttl(&mut self, key: Bytes, value: Bytes, ttl: Duration) -> Result<SequenceNumber> {
+        self.add(ValueType::Value, key, value, Some(ttl))
+    }
+
+    pub fn delete(&mut self, key: Bytes) -> Result<SequenceNumber> {
+        self.add(ValueType::Deletion, key, Bytes::new(), None)
+    }
+
+    pub fn delete_range(&mut self, start: Bytes, end: Bytes) -> Result<SequenceNumber> {
+        self.check_key_size(&start)?;
+        self.check_key_size(&end)?;
+        if start > end {
+            return Err(Error::InvalidArgument(String::from("start key of the range is greater than the end key")));
+        }
+        self.last_sequence += 1;
+        self.range_tombstones.add(start, end, self.last_sequence);
+        Ok(self.last_sequence)
+    }
+
+    pub fn merge(&mut self, key: Bytes, operand: Bytes) -> Result<SequenceNumber> {
+        if self.merge_operator.is_none() {
+            return Err(Error::InvalidArgument(String::from("merge operator is not set")));
+        }
+        self.add(ValueType::Merge, key, operand, self.default_ttl)
+    }
+
+    // Writes the new value only when the current value equals the expected one,
+    // where None stands for an absent key. Returns None if nothing was written.
+    pub fn cas(&mut self, key: Bytes, expected: Option<&Bytes>, new: Bytes) -> Result<Option<SequenceNumber>> {
+        if self.get(&key).as_ref() != expected {
+            return Ok(None);
+        }
+        self.put(key, new).map(Some)
+    }
+
+    pub fn put_if_absent(&mut self, key: Bytes, value: Bytes) -> Result<Option<SequenceNumber>> {
+        self.cas(key, None, value)
+    }
+
+    pub fn iter(&self) -> MemTableIterator<'_> {
+        self.scan(..)
+    }
+
+    pub fn scan<R: RangeBounds<Bytes>>(&self, range: R) -> MemTableIterator<'_> {
+        self.scan_at(range, self.last_sequence)
+    }
+
+    // Scans the keys in the range as of the given sequence number.
+    pub fn scan_at<R: RangeBounds<Bytes>>(&self, range: R, sequence: SequenceNumber) -> MemTableIterator<'_> {
+        MemTableIterator {
+            memtable: self,
+            sequence,
+            front: range.start_bound().cloned(),
+            back: range.end_bound().cloned(),
+        }
+    }
+
+    // Exports the range as of the sequence number in chunks of up to chunk_size entries.
+    // Later writes never show up, so the export can be resumed from any chunk.
+    pub fn export_stream<R: RangeBounds<Bytes>>(
+        &self,
+        sequence: SequenceNumber,
+        range: R,
+        chunk_size: usize,
+    ) -> Result<ExportStream<'_>> {
+        if chunk_size == 0 {
+            return Err(Error::InvalidArgument(String::from("chunk size must be positive")));
+        }
+        if sequence > self.last_sequence {
+            return Err(Error::InvalidArgument(format!(
+                "sequence {} is newer than the last sequence {}", sequence, self.last_sequence
+            )));
+        }
+        Ok(ExportStream::new(self.scan_at(range, sequence), sequence, chunk_size))
+    }
+
+    pub fn keys<R: RangeBounds<Bytes>>(&self, range: R) -> KeysIterator<'_> {
+        KeysIterator { inner: self.scan(range) }
+    }
+
+    pub fn values<R: RangeBounds<Bytes>>(&self, range: R) -> impl DoubleEndedIterator<Item = Bytes> + '_ {
+        self.scan(range).map(|(_, value)| value)
+    }
+
+    // Scans the keys starting with the prefix, which are exactly those below the
+    // smallest key greater than every key with the prefix.
+    pub fn scan_prefix(&self, prefix: &Bytes) -> MemTableIterator<'_> {
+        let mut successor = prefix.to_vec();
+        while successor.last() == Some(&u8::MAX) {
+            successor.pop();
+        }
+        let end = match successor.last_mut() {
+            Some(last) => {
+                *last += 1;
+                Bound::Excluded(Bytes::from(successor))
+            }
+            None => Bound::Unbounded,
+        };
+        self.scan((Bound::Included(prefix.clone()), end))
+    }
+
+    // Counts every version and tombstone written to the memtable, so it never
+    // underestimates the number of visible keys.
+    pub fn estimate_num_keys(&self) -> usize {
+        self.num_entries
+    }
+
+    pub fn count_range<R: RangeBounds<Bytes>>(&self, range: R) -> usize {
+        self.keys(range).count()
+    }
+
+    // Returns the last visible entry whose key is less than or equal to the given one.
+    pub fn seek_for_prev(&self, key: &Bytes) -> Option<(Bytes, Bytes)> {
+        self.scan(..=key.clone()).next_back()
+    }
+
+    pub fn get(&self, key: &Bytes) -> Option<Bytes> {
+        self.get_at(key, self.last_sequence)
+    }
+
+    pub fn get_at(&self, key: &Bytes, sequence: SequenceNumber) -> Option<Bytes> {
+        match self.lookup(key, sequence) {
+            LookupResult::Found(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    // Returns false only when the key definitely has no visible value.
+    pub fn key_may_exist(&self, key: &Bytes) -> bool {
+        self.is_visible(key, self.last_sequence)
+    }
+
+    // Looks the keys up in sorted order so that duplicated keys share a single lookup,
+    // and returns the results in the order of the given keys.
+    pub fn multi_get(&self, keys: &[Bytes]) -> Vec<Option<Bytes>> {
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+        let mut results = vec![None; keys.len()];
+        let mut prev: Option<usize> = None;
+        for i in order {
+            results[i] = match prev {
+                Some(p) if keys[p] == keys[i] => results[p].clone(),
+                _ => self.get(&keys[i]),
+            };
+            prev = Some(i);
+        }
+        results
+    }
+
+    // Tells whether lookup would find a value without reading or merging it.
+    // Only the newest entry visible at the sequence number decides it.
+    fn is_visible(&self, key: &Bytes, sequence: SequenceNumber) -> bool {
+        let lookup_key = InternalKey::new(key.clone(), sequence, VALUE_TYPE_FOR_SEEK);
+        let range_deleted_at = self.range_tombstones.max_covering(key, sequence);
+        match self.table.iter_from(&lookup_key).next() {
+            Some((found, _)) if found.user_key != key => false,
+            Some((found, _)) if matches!(range_deleted_at, Some(deleted_at) if found.sequence < deleted_at) => false,
+            Some((found, entry)) => found.value_type != ValueType::Deletion && !entry.is_expired(),
+            None => false,
+        }
+    }
+
+    // Resolves the newest entry of the key visible at the given sequence number.
+    // Deleted means a tombstone or an expired value or operand hides any older value stored below
+    // this memtable.
+    pub fn lookup(&self, key: &Bytes, sequence: SequenceNumber) -> LookupResult {
+        let lookup_key = InternalKey::new(key.clone(), sequence, VALUE_TYPE_FOR_SEEK);
+        let range_deleted_at = self.range_tombstones.max_covering(key, sequence);
+        let mut operands = Vec::new();
+        let mut base = match range_deleted_at {
+            Some(_) => LookupResult::Deleted,
+            None => LookupResult::NotFound,
+        };
+        for (found, entry) in self.table.iter_from(&lookup_key) {
+            if found.user_key != key {
+                break;
+            }
+            if matches!(range_deleted_at, Some(deleted_at) if found.sequence < deleted_at) {
+                break;
+            }
+            match found.value_type {
+                ValueType::Value | ValueType::Merge if entry.is_expired() => {
+                    base = LookupResult::Deleted;
+                    break;
+                }
+                ValueType::Merge => operands.push(entry.value),
+                ValueType::Value => {
+                    base = LookupResult::Found(entry.value);
+                    break;
+                }
+                ValueType::Deletion => {
+                    base = LookupResult::Deleted;
+                    break;
+                }
+            }
+        }
+        if operands.is_empty() {
+            return base;
+        }
+        operands.reverse();
+        let existing = match &base {
+            LookupResult::Found(value) => Some(value),
+            _ => None,
+        };
+        // Merge entries are only written when a merge operator is set.
+        let merge_operator = self.merge_operator.as_ref().unwrap();
+        LookupResult::Found(merge_operator.full_merge(key, existing, &operands))
+    }
+}
+
+// Yields the newest visible value of every user key in order, skipping deleted,
+// expired and range-deleted keys and collapsing merge operands. Iterating from
+// both ends stops where the two directions meet.
+pub struct MemTableIterator<'a> {
+    memtable: &'a MemTable,
+    sequence: SequenceNumber,
+    front: Bound<Bytes>,
+    back: Bound<Bytes>,
+}
+
+impl MemTableIterator<'_> {
+    fn after_front(&self, key: &Bytes) -> bool {
+        match &self.front {
+            Bound::Unbounded => true,
+            Bound::Included(front) => key >= front,
+            Bound::Excluded(front) => key > front,
+        }
+    }
+
+    fn before_back(&self, key: &Bytes) -> bool {
+        match &self.back {
+            Bound::Unbounded => true,
+            Bound::Included(back) => key <= back,
+            Bound::Excluded(back) => key < back,
+        }
+    }
+
+    // Moves the front bound past the next user key in the range and returns it.
+    fn advance_front(&mut self) -> Option<Bytes> {
+        let seek_key = match &self.front {
+            Bound::Unbounded => InternalKey::first_of(Bytes::new()),
+            Bound::Included(key) => InternalKey::first_of(key.clone()),
+            Bound::Excluded(key) => InternalKey::last_of(key.clone()),
+        };
+        let (found, _) = self.memtable.table.iter_from(&seek_key).next()?;
+        let key = found.user_key;
+        if !self.before_back(&key) {
+            return None;
+        }
+        self.front = Bound::Excluded(key.clone());
+        Some(key)
+    }
+
+    // Moves the back bound before the previous user key in the range and returns it.
+    fn advance_back(&mut self) -> Option<Bytes> {
+        let table = &self.memtable.table;
+        let found = match &self.back {
+            Bound::Unbounded => table.last(),
+            Bound::Included(key) => table.get_less_than(&InternalKey::last_of(key.clone())),
+            Bound::Excluded(key) => table.get_less_than(&InternalKey::first_of(key.clone())),
+        };
+        let key = found?.0.user_key;
+        if !self.after_front(&key) {
+            return None;
+        }
+        self.back = Bound::Excluded(key.clone());
+        Some(key)
+    }
+
+    // Returns up to n entries, fewer only when the scan is exhausted.
+    pub fn next_batch(&mut self, n: usize) -> Vec<(Bytes, Bytes)> {
+        self.by_ref().take(n).collect()
+    }
+}
+
+impl Iterator for MemTableIterator<'_> {
+    type Item = (Bytes, Bytes);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.advance_front()?;
+            if let LookupResult::Found(value) = self.memtable.lookup(&key, self.sequence) {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+impl DoubleEndedIterator for MemTableIterator<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.advance_back()?;
+            if let LookupResult::Found(value) = self.memtable.lookup(&key, self.sequence) {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+// Yields the keys of a scan, checking only their visibility so that values are
+// neither copied nor merged.
+pub struct KeysIterator<'a> {
+    inner: MemTableIterator<'a>,
+}
+
+impl Iterator for KeysIterator<'_> {
+    type Item = Bytes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.inner.advance_front()?;
+            if self.inner.memtable.is_visible(&key, self.inner.sequence) {
+                return Some(key);
+            }
+        }
+    }
+}
+
+impl DoubleEndedIterator for KeysIterator<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.inner.advance_back()?;
+            if self.inner.memtable.is_visible(&key, self.inner.sequence) {
+                return Some(key);
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a MemTable {
+    type Item = (Bytes, Bytes);
+    type IntoIter = MemTableIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::collections::BTreeMap;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use alloc::{format, vec};
+    use core::cell::Cell;
+    use core::ops::Bound;
+    #[cfg(feature = "std")]
+    use core::time::Duration;
+
+    use bytes::Bytes;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::data::error::Error;
+    use crate::data::export::ExportChunk;
+    use crate::data::memtable::{LookupResult, MemTable, MemTableIterator};
+    use crate::data::merge_operator::MergeOperator;
+
+    struct AppendOperator;
+
+    impl MergeOperator for AppendOperator {
+        fn full_merge(&self, _key: &Bytes, existing: Option<&Bytes>, operands: &[Bytes]) -> Bytes {
+            let mut merged = existing.map(|v| v.to_vec()).unwrap_or_default();
+            for operand in operands {
+                if !merged.is_empty() {
+                    merged.push(b',');
+                }
+                merged.extend_from_slice(operand);
+            }
+            Bytes::from(merged)
+        }
+    }
+
+    #[test]
+    fn newest_version_wins() {
+        let mut memtable = MemTable::new();
+        let key = Bytes::from("hello");
+        let first = memtable.put(key.clone(), Bytes::from("world")).unwrap();
+        let second = memtable.put(key.clone(), Bytes::from("rust")).unwrap();
+        assert!(first < second);
+        assert_eq!(memtable.last_sequence(), second);
+        assert_eq!(memtable.get(&key), Some(Bytes::from("rust")));
+        assert_eq!(memtable.get(&Bytes::from("hell")), None);
+    }
+
+    #[test]
+    fn get_at_snapshot() {
+        let mut memtable = MemTable::new();
+        let key = Bytes::from("hello");
+        let first = memtable.put(key.clone(), Bytes::from("world")).unwrap();
+        memtable.put(key.clone(), Bytes::from("rust")).unwrap();
+        assert_eq!(memtable.get_at(&key, first), Some(Bytes::from("world")));
+        assert_eq!(memtable.get_at(&key, first - 1), None);
+    }
+
+    #[test]
+    fn tombstone_hides_older_value() {
+        let mut memtable = MemTable::new();
+        let key = Bytes::from("hello");
+        let written = memtable.put(key.clone(), Bytes::from("world")).unwrap();
+        let deleted = memtable.delete(key.clone()).unwrap();
+        assert_eq!(memtable.get(&key), None);
+        assert_eq!(memtable.lookup(&key, deleted), LookupResult::Deleted);
+        assert_eq!(memtable.lookup(&key, written), LookupResult::Found(Bytes::from("world")));
+        assert_eq!(memtable.lookup(&Bytes::from("other"), deleted), LookupResult::NotFound);
+
+        memtable.put(key.clone(), Bytes::from("again")).unwrap();
+        assert_eq!(memtable.get(&key), Some(Bytes::from("again")));
+    }
+
+    #[test]
+    fn multi_get_keeps_input_order() {
+        let mut memtable = MemTable::new();
+        memtable.put(Bytes::from("a"), Bytes::from("1")).unwrap();
+        memtable.put(Bytes::from("b"), Bytes::from("2")).unwrap();
+        memtable.delete(Bytes::from("b")).unwrap();
+        memtable.put(Bytes::from("c"), Bytes::from("3")).unwrap();
+        let keys = vec![
+            Bytes::from("c"),
+            Bytes::from("x"),
+            Bytes::from("a"),
+            Bytes::from("b"),
+            Bytes::from("c"),
+        ];
+        let got = memtable.multi_get(&keys);
+        assert_eq!(got, vec![
+            Some(Bytes::from("3")),
+            None,
+            Some(Bytes::from("1")),
+            None,
+            Some(Bytes::from("3")),
+        ]);
+    }
+
+    #[test]
+    fn cas_and_put_if_absent() {
+        let mut memtable = MemTable::new();
+        let key = Bytes::from("counter");
+        assert!(memtable.put_if_absent(key.clone(), Bytes::from("1")).unwrap().is_some());
+        assert!(memtable.put_if_absent(key.clone(), Bytes::from("9")).unwrap().is_none());
+        assert!(memtable.cas(key.clone(), Some(&Bytes::from("0")), Bytes::from("2")).unwrap().is_none());
+        assert!(memtable.cas(key.clone(), Some(&Bytes::from("1")), Bytes::from("2")).unwrap().is_some());
+        assert_eq!(memtable.get(&key), Some(Bytes::from("2")));
+
+        memtable.delete(key.clone()).unwrap();
+        assert!(memtable.cas(key.clone(), None, Bytes::from("3")).unwrap().is_some());
+        assert_eq!(memtable.get(&key), Some(Bytes::from("3")));
+    }
+
+    #[test]
+    fn merge_operands_are_collapsed_on_get() {
+        let mut memtable = MemTable::with_merge_operator(Box::new(AppendOperator));
+        let key = Bytes::from("list");
+        memtable.merge(key.clone(), Bytes::from("a")).unwrap();
+        assert_eq!(memtable.get(&key), Some(Bytes::from("a")));
+
+        memtable.put(key.clone(), Bytes::from("b")).unwrap();
+        let merged = memtable.merge(key.clone(), Bytes::from("c")).unwrap();
+        memtable.merge(key.clone(), Bytes::from("d")).unwrap();
+        assert_eq!(memtable.get(&key), Some(Bytes::from("b,c,d")));
+        assert_eq!(memtable.get_at(&key, merged), Some(Bytes::from("b,c")));
+
+        memtable.delete(key.clone()).unwrap();
+        memtable.merge(key.clone(), Bytes::from("e")).unwrap();
+        assert_eq!(memtable.get(&key), Some(Bytes::from("e")));
+    }
+
+    #[test]
+    fn merge_without_operator_is_rejected() {
+        let mut memtable = MemTable::new();
+        assert!(matches!(
+            memtable.merge(Bytes::from("key"), Bytes::from("operand")),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(memtable.last_sequence(), 0);
+        assert_eq!(memtable.get(&Bytes::from("key")), None);
+    }
+
+    #[test]
+    fn size_limits_are_enforced() {
+        let mut memtable = MemTable::new();
+        memtable.set_max_key_size(4);
+        memtable.set_max_value_size(8);
+        assert!(memtable.put(Bytes::from("key"), Bytes::from("value")).is_ok());
+        assert!(matches!(
+            memtable.put(Bytes::from("long key"), Bytes::from("value")),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            memtable.put(Bytes::from("key"), Bytes::from("long value")),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(memtable.delete_range(Bytes::from("a"), Bytes::from("long key")).is_err());
+        assert_eq!(memtable.last_sequence(), 1);
+        assert_eq!(memtable.get(&Bytes::from("key")), Some(Bytes::from("value")));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn huge_ttls_do_not_overflow() {
+        let mut memtable = MemTable::new();
+        memtable.put_with_ttl(Bytes::from("a"), Bytes::from("1"), Duration::MAX).unwrap();
+        memtable.put_with_ttl(Bytes::from("b"), Bytes::from("2"), Duration::from_secs(u64::MAX / 1000)).unwrap();
+        assert_eq!(memtable.get(&Bytes::from("a")), Some(Bytes::from("1")));
+        assert_eq!(memtable.get(&Bytes::from("b")), Some(Bytes::from("2")));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn expired_values_are_invisible() {
+        let mut memtable = MemTable::new();
+        let key = Bytes::from("session");
+        memtable.put(key.clone(), Bytes::from("old")).unwrap();
+        memtable.put_with_ttl(key.clone(), Bytes::from("new"), Duration::from_secs(3600)).unwrap();
+        assert_eq!(memtable.get(&key), Some(Bytes::from("new")));
+
+        let expired = memtable.put_with_ttl(key.clone(), Bytes::from("gone"), Duration::ZERO).unwrap();
+        assert_eq!(memtable.lookup(&key, expired), LookupResult::Deleted);
+
+        memtable.set_default_ttl(Some(Duration::ZERO));
+        let other = Bytes::from("other");
+        memtable.put(other.clone(), Bytes::from("value")).unwrap();
+        assert_eq!(memtable.get(&other), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn expired_merge_operands_are_invisible() {
+        let mut memtable = MemTable::with_merge_operator(Box::new(AppendOperator));
+        let key = Bytes::from("counter");
+        memtable.put(key.clone(), Bytes::from("1")).unwrap();
+        memtable.set_default_ttl(Some(Duration::ZERO));
+        memtable.merge(key.clone(), Bytes::from("2")).unwrap();
+        assert_eq!(memtable.get(&key), None);
+
+        memtable.merge(Bytes::from("fresh"), Bytes::from("1")).unwrap();
+        assert_eq!(memtable.get(&Bytes::from("fresh")), None);
+
+        memtable.set_default_ttl(Some(Duration::from_secs(3600)));
+        memtable.merge(key.clone(), Bytes::from("3")).unwrap();
+        assert_eq!(memtable.get(&key), Some(Bytes::from("3")));
+    }
+
+    #[test]
+    fn delete_range_hides_covered_keys() {
+        let mut memtable = MemTable::new();
+        for key in ["a", "b", "c", "d"] {
+            memtable.put(Bytes::from(key), Bytes::from("old")).unwrap();
+        }
+        let deleted = memtable.delete_range(Bytes::from("b"), Bytes::from("d")).unwrap();
+        memtable.put(Bytes::from("c"), Bytes::from("new")).unwrap();
+
+        assert_eq!(memtable.get(&Bytes::from("a")), Some(Bytes::from("old")));
+        assert_eq!(memtable.lookup(&Bytes::from("b"), deleted), LookupResult::Deleted);
+        assert_eq!(memtable.lookup(&Bytes::from("bb"), deleted), LookupResult::Deleted);
+        assert_eq!(memtable.get(&Bytes::from("c")), Some(Bytes::from("new")));
+        assert_eq!(memtable.get(&Bytes::from("d")), Some(Bytes::from("old")));
+        assert_eq!(memtable.get_at(&Bytes::from("b"), deleted - 1), Some(Bytes::from("old")));
+    }
+
+    #[test]
+    fn key_may_exist_follows_deletions() {
+        let mut memtable = MemTable::with_merge_operator(Box::new(AppendOperator));
+        for key in ["a", "b", "c"] {
+            memtable.put(Bytes::from(key), Bytes::new()).unwrap();
+        }
+        memtable.delete(Bytes::from("a")).unwrap();
+        memtable.delete_range(Bytes::from("b"), Bytes::from("c")).unwrap();
+        memtable.merge(Bytes::from("d"), Bytes::from("1")).unwrap();
+        assert!(!memtable.key_may_exist(&Bytes::from("a")));
+        assert!(!memtable.key_may_exist(&Bytes::from("b")));
+        assert!(memtable.key_may_exist(&Bytes::from("c")));
+        assert!(memtable.key_may_exist(&Bytes::from("d")));
+        assert!(!memtable.key_may_exist(&Bytes::from("e")));
+    }
+
+    #[test]
+    fn delete_range_rejects_reversed_bounds() {
+        let mut memtable = MemTable::new();
+        memtable.put(Bytes::from("b"), Bytes::from("value")).unwrap();
+        assert!(matches!(
+            memtable.delete_range(Bytes::from("c"), Bytes::from("a")),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(memtable.last_sequence(), 1);
+        assert!(memtable.delete_range(Bytes::from("b"), Bytes::from("b")).is_ok());
+        assert_eq!(memtable.get(&Bytes::from("b")), Some(Bytes::from("value")));
+    }
+
+    #[test]
+    fn iter_yields_visible_keys_in_order() {
+        let mut memtable = MemTable::with_merge_operator(Box::new(AppendOperator));
+        for key in ["e", "a", "c", "b", "d", "f"] {
+            memtable.put(Bytes::from(key), Bytes::from("v")).unwrap();
+        }
+        memtable.put(Bytes::from("a"), Bytes::from("new")).unwrap();
+        memtable.delete(Bytes::from("b")).unwrap();
+        memtable.delete_range(Bytes::from("d"), Bytes::from("f")).unwrap();
+        memtable.merge(Bytes::from("c"), Bytes::from("m")).unwrap();
+        memtable.merge(Bytes::from("g"), Bytes::from("m")).unwrap();
+
+        let mut entries = Vec::new();
+        for (key, value) in &memtable {
+            entries.push((key, value));
+        }
+        assert_eq!(entries, vec![
+            (Bytes::from("a"), Bytes::from("new")),
+            (Bytes::from("c"), Bytes::from("v,m")),
+            (Bytes::from("f"), Bytes::from("v")),
+            (Bytes::from("g"), Bytes::from("m")),
+        ]);
+        assert_eq!(memtable.iter().filter(|(_, value)| value == "v").count(), 1);
+    }
+
+    #[test]
+    fn scan_stops_at_bounds() {
+        let mut memtable = MemTable::new();
+        for key in ["a", "b", "c", "d"] {
+            memtable.put(Bytes::from(key), Bytes::from(key)).unwrap();
+        }
+        let keys = |iter: MemTableIterator| iter.map(|(k, _)| k).collect::<Vec<Bytes>>();
+        assert_eq!(keys(memtable.scan(Bytes::from("b")..Bytes::from("d"))), vec![Bytes::from("b"), Bytes::from("c")]);
+        assert_eq!(keys(memtable.scan(Bytes::from("bb")..=Bytes::from("d"))), vec![Bytes::from("c"), Bytes::from("d")]);
+        assert_eq!(keys(memtable.scan(..Bytes::from("b"))), vec![Bytes::from("a")]);
+        assert_eq!(keys(memtable.scan((Bound::Excluded(Bytes::from("c")), Bound::Unbounded))), vec![Bytes::from("d")]);
+
+        let written = memtable.last_sequence();
+        memtable.delete(Bytes::from("a")).unwrap();
+        assert_eq!(keys(memtable.scan(..Bytes::from("b"))), Vec::<Bytes>::new());
+        assert_eq!(keys(memtable.scan_at(..Bytes::from("b"), written)), vec![Bytes::from("a")]);
+    }
+
+    #[test]
+    fn keys_and_values_in_range() {
+        let mut memtable = MemTable::new();
+        for (key, value) in [("a", "1"), ("b", "2"), ("c", "3"), ("d", "4")] {
+            memtable.put(Bytes::from(key), Bytes::from(value)).unwrap();
+        }
+        memtable.delete(Bytes::from("c")).unwrap();
+        let keys: Vec<Bytes> = memtable.keys(Bytes::from("b")..).collect();
+        assert_eq!(keys, vec![Bytes::from("b"), Bytes::from("d")]);
+        let values: Vec<Bytes> = memtable.values(..Bytes::from("d")).rev().collect();
+        assert_eq!(values, vec![Bytes::from("2"), Bytes::from("1")]);
+    }
+
+    #[test]
+    fn scan_prefix_stops_at_the_first_key_without_it() {
+        let mut memtable = MemTable::new();
+        let keys = [&b"a"[..], b"ab", b"abc", b"abd", b"ac", b"b", b"\xff", b"\xff\xff", b"\xff\xff\x00", b"\xff\xff\xff"];
+        for key in keys {
+            memtable.put(Bytes::copy_from_slice(key), Bytes::new()).unwrap();
+        }
+        memtable.delete(Bytes::from("abc")).unwrap();
+        let scanned = |prefix: &[u8]| {
+            memtable.scan_prefix(&Bytes::copy_from_slice(prefix)).map(|(k, _)| k).collect::<Vec<Bytes>>()
+        };
+        assert_eq!(scanned(b"ab"), vec![Bytes::from("ab"), Bytes::from("abd")]);
+        assert_eq!(scanned(b"abc"), Vec::<Bytes>::new());
+        assert_eq!(scanned(b"").len(), keys.len() - 1);
+        assert_eq!(scanned(b"\xff\xff"), vec![
+            Bytes::from_static(b"\xff\xff"),
+            Bytes::from_static(b"\xff\xff\x00"),
+            Bytes::from_static(b"\xff\xff\xff"),
+        ]);
+    }
+
+    #[test]
+    fn count_range_and_estimate_num_keys() {
+        let mut memtable = MemTable::new();
+        assert_eq!(memtable.estimate_num_keys(), 0);
+        for key in ["a", "b", "c", "d", "e"] {
+            memtable.put(Bytes::from(key), Bytes::new()).unwrap();
+        }
+        memtable.put(Bytes::from("a"), Bytes::new()).unwrap();
+        memtable.delete(Bytes::from("b")).unwrap();
+        memtable.delete_range(Bytes::from("d"), Bytes::from("e")).unwrap();
+        assert_eq!(memtable.count_range(..), 3);
+        assert_eq!(memtable.count_range(Bytes::from("b")..Bytes::from("e")), 1);
+        assert_eq!(memtable.count_range(Bytes::from("b")..=Bytes::from("e")), 2);
+        assert_eq!(memtable.estimate_num_keys(), 7);
+    }
+
+    #[test]
+    fn export_stream_resumes_at_the_snapshot() {
+        let mut memtable = MemTable::new();
+        for i in 0..10 {
+            memtable.put(Bytes::from(format!("key{}", i)), Bytes::from("old")).unwrap();
+        }
+        let snapshot = memtable.last_sequence();
+        let expected: Vec<(Bytes, Bytes)> = memtable.iter().collect();
+
+        let mut exported = Vec::new();
+        let first = memtable.export_stream(snapshot, .., 4).unwrap().next().unwrap();
+        assert!(first.verify());
+        assert_eq!(first.entries.len(), 4);
+        exported.extend(first.entries.clone());
+
+        memtable.put(Bytes::from("key5"), Bytes::from("new")).unwrap();
+        memtable.delete(Bytes::from("key7")).unwrap();
+        memtable.put(Bytes::from("key55"), Bytes::from("new")).unwrap();
+        let chunks: Vec<ExportChunk> = memtable
+            .export_stream(first.sequence, (first.resume_after(), Bound::Unbounded), 4)
+            .unwrap()
+            .collect();
+        assert_eq!(chunks.iter().map(|c| c.entries.len()).collect::<Vec<usize>>(), vec![4, 2]);
+        for chunk in chunks {
+            assert!(chunk.verify());
+            assert_eq!(chunk.sequence, snapshot);
+            exported.extend(chunk.entries);
+        }
+        assert_eq!(exported, expected);
+
+        let mut tampered = first;
+        tampered.entries[0].1 = Bytes::from("new");
+        assert!(!tampered.verify());
+        assert!(memtable.export_stream(snapshot, .., 0).is_err());
+        assert!(memtable.export_stream(memtable.last_sequence() + 1, .., 4).is_err());
+    }
+
+    struct CountingOperator(Rc<Cell<usize>>);
+
+    impl MergeOperator for CountingOperator {
+        fn full_merge(&self, _key: &Bytes, _existing: Option<&Bytes>, _operands: &[Bytes]) -> Bytes {
+            self.0.set(self.0.get() + 1);
+            Bytes::new()
+        }
+    }
+
+    #[test]
+    fn keys_do_not_merge_values() {
+        let merges = Rc::new(Cell::new(0));
+        let mut memtable = MemTable::with_merge_operator(Box::new(CountingOperator(merges.clone())));
+        for i in 0..10 {
+            memtable.merge(Bytes::from(format!("key{}", i)), Bytes::from("1")).unwrap();
+        }
+        memtable.delete(Bytes::from("key3")).unwrap();
+        memtable.delete_range(Bytes::from("key5"), Bytes::from("key7")).unwrap();
+        assert_eq!(memtable.keys(..).count(), 7);
+        assert_eq!(memtable.keys(..).rev().count(), 7);
+        assert_eq!(merges.get(), 0);
+    }
+
+    #[test]
+    fn next_batch_returns_up_to_n_entries() {
+        let mut memtable = MemTable::new();
+        for key in ["a", "b", "c", "d", "e"] {
+            memtable.put(Bytes::from(key), Bytes::from(key)).unwrap();
+        }
+        memtable.delete(Bytes::from("b")).unwrap();
+        let mut iter = memtable.iter();
+        let keys = |batch: Vec<(Bytes, Bytes)>| batch.into_iter().map(|(k, _)| k).collect::<Vec<Bytes>>();
+        assert_eq!(keys(iter.next_batch(2)), vec![Bytes::from("a"), Bytes::from("c")]);
+        assert_eq!(keys(iter.next_batch(3)), vec![Bytes::from("d"), Bytes::from("e")]);
+        assert!(iter.next_batch(3).is_empty());
+        assert!(memtable.iter().next_batch(0).is_empty());
+    }
+
+    #[test]
+    fn reverse_scan_and_seek_for_prev() {
+        let mut memtable = MemTable::new();
+        for key in ["a", "b", "c", "d", "e"] {
+            memtable.put(Bytes::from(key), Bytes::from(key)).unwrap();
+        }
+        memtable.delete(Bytes::from("d")).unwrap();
+        let keys: Vec<Bytes> = memtable.scan(Bytes::from("b")..).rev().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![Bytes::from("e"), Bytes::from("c"), Bytes::from("b")]);
+
+        let mut iter = memtable.iter();
+        assert_eq!(iter.next().unwrap().0, Bytes::from("a"));
+        assert_eq!(iter.next_back().unwrap().0, Bytes::from("e"));
+        assert_eq!(iter.next_back().unwrap().0, Bytes::from("c"));
+        assert_eq!(iter.next().unwrap().0, Bytes::from("b"));
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+
+        assert_eq!(memtable.seek_for_prev(&Bytes::from("d")).unwrap().0, Bytes::from("c"));
+        assert_eq!(memtable.seek_for_prev(&Bytes::from("bb")).unwrap().0, Bytes::from("b"));
+        assert_eq!(memtable.seek_for_prev(&Bytes::from("e")).unwrap().0, Bytes::from("e"));
+        assert!(memtable.seek_for_prev(&Bytes::from("0")).is_none());
+    }
+
+    // Applies random operations to both the memtable and a BTreeMap model,
+    // and checks that every key reads the same, also at older snapshots.
+    #[test]
+    fn matches_btree_map_model() {
+        let keys: Vec<Bytes> = (0..16).map(|i| Bytes::from(format!("key{:02}", i))).collect();
+        for seed in 0..16 {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let mut memtable = MemTable::with_merge_operator(Box::new(AppendOperator));
+            let mut model: BTreeMap<Bytes, Bytes> = BTreeMap::new();
+            let mut snapshots = Vec::new();
+            for i in 0..500 {
+                let key = keys[rng.gen_range(0..keys.len())].clone();
+                match rng.gen_range(0..10) {
+                    0..=3 => {
+                        let value = Bytes::from(format!("v{}", i));
+                        memtable.put(key.clone(), value.clone()).unwrap();
+                        model.insert(key, value);
+                    }
+                    4..=5 => {
+                        memtable.delete(key.clone()).unwrap();
+                        model.remove(&key);
+                    }
+                    6 => {
+                        let other = keys[rng.gen_range(0..keys.len())].clone();
+                        let (start, end) = if key <= other { (key, other) } else { (other, key) };
+                        memtable.delete_range(start.clone(), end.clone()).unwrap();
+                        model.retain(|k, _| !(start <= k && *k < end));
+                    }
+                    7..=8 => {
+                        let operand = format!("m{}", i);
+                        memtable.merge(key.clone(), Bytes::from(operand.clone())).unwrap();
+                        let merged = match model.get(&key) {
+                            Some(existing) => format!("{},{}", core::str::from_utf8(existing).unwrap(), operand),
+                            None => operand,
+                        };
+                        model.insert(key, Bytes::from(merged));
+                    }
+                    _ => snapshots.push((memtable.last_sequence(), model.clone())),
+                }
+                for key in &keys {
+                    assert_eq!(memtable.get(key), model.get(key).cloned(), "seed {} op {} key {:?}", seed, i, key);
+                }
+                let scanned: Vec<(Bytes, Bytes)> = memtable.iter().collect();
+                let expected: Vec<(Bytes, Bytes)> = model.clone().into_iter().collect();
+                assert_eq!(scanned, expected, "seed {} op {}", seed, i);
+                let visible: Vec<Bytes> = memtable.keys(..).collect();
+                assert_eq!(visible, model.keys().cloned().collect::<Vec<Bytes>>(), "seed {} op {}", seed, i);
+                let reversed: Vec<(Bytes, Bytes)> = memtable.iter().rev().collect();
+                let expected: Vec<(Bytes, Bytes)> = model.clone().into_iter().rev().collect();
+                assert_eq!(reversed, expected, "seed {} op {}", seed, i);
+                let lower = keys[rng.gen_range(0..keys.len())].clone();
+                let upper = keys[rng.gen_range(0..keys.len())].clone();
+                if lower <= upper {
+                    let scanned: Vec<(Bytes, Bytes)> = memtable.scan(lower.clone()..upper.clone()).collect();
+                    let expected: Vec<(Bytes, Bytes)> = model.range(lower.clone()..upper.clone()).map(|(k, v)| (k.clone(), v.clone())).collect();
+                    assert_eq!(scanned, expected, "seed {} op {}", seed, i);
+                    assert_eq!(memtable.count_range(lower..upper), expected.len(), "seed {} op {}", seed, i);
+                }
+            }
+            for (sequence, snapshot) in snapshots {
+                for key in &keys {
+                    assert_eq!(memtable.get_at(key, sequence), snapshot.get(key).cloned(), "seed {} key {:?}", seed, key);
+                }
+                let scanned: Vec<(Bytes, Bytes)> = memtable.scan_at(.., sequence).collect();
+                let expected: Vec<(Bytes, Bytes)> = snapshot.into_iter().collect();
+                assert_eq!(scanned, expected, "seed {} sequence {}", seed, sequence);
+            }
+        }
+    }
+}
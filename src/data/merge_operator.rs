@@ -0,0 +1,6 @@
+use bytes::Bytes;
+
+pub trait MergeOperator {
+    // Operands are given from the oldest to the newest one.
+    fn full_merge(&self, key: &Bytes, existing: Option<&Bytes>, operands: &[Bytes]) -> Bytes;
+}
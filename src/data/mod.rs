@@ -1 +1,7 @@
+pub mod error;
+pub mod export;
+pub mod internal_key;
+pub mod memtable;
+pub mod merge_operator;
+pub mod range_tombstone;
 pub mod skiplist;
\ No newline at end of file
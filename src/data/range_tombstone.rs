@@ -0,0 +1,105 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use bytes::Bytes;
+
+use crate::data::internal_key::SequenceNumber;
+
+struct Fragment {
+    end: Bytes,
+    // Sequence numbers of the range deletions covering the fragment, in increasing order
+    sequences: Vec<SequenceNumber>,
+}
+
+// Range deletions split into non-overlapping fragments keyed by their start key,
+// so that finding the tombstones covering a key takes a single ordered lookup.
+#[derive(Default)]
+pub struct RangeTombstones {
+    fragments: BTreeMap<Bytes, Fragment>,
+}
+
+impl RangeTombstones {
+    pub fn new() -> Self {
+        RangeTombstones {
+            fragments: BTreeMap::new(),
+        }
+    }
+
+    // Makes sure no fragment spans across the key.
+    fn split_at(&mut self, key: &Bytes) {
+        let start = match self.fragments.range(..key.clone()).next_back() {
+            Some((start, fragment)) if *key < fragment.end => start.clone(),
+            _ => return,
+        };
+        let fragment = self.fragments.get_mut(&start).unwrap();
+        let right = Fragment {
+            end: mem::replace(&mut fragment.end, key.clone()),
+            sequences: fragment.sequences.clone(),
+        };
+        self.fragments.insert(key.clone(), right);
+    }
+
+    // Sequence numbers must be given in increasing order.
+    pub fn add(&mut self, start: Bytes, end: Bytes, sequence: SequenceNumber) {
+        if start >= end {
+            return;
+        }
+        self.split_at(&start);
+        self.split_at(&end);
+        let mut gaps = Vec::new();
+        let mut cursor = start.clone();
+        for (fragment_start, fragment) in self.fragments.range_mut(start..end.clone()) {
+            if cursor < *fragment_start {
+                gaps.push((cursor, fragment_start.clone()));
+            }
+            fragment.sequences.push(sequence);
+            cursor = fragment.end.clone();
+        }
+        if cursor < end {
+            gaps.push((cursor, end));
+        }
+        for (gap_start, gap_end) in gaps {
+            self.fragments.insert(gap_start, Fragment {
+                end: gap_end,
+                sequences: vec![sequence],
+            });
+        }
+    }
+
+    // Returns the newest range deletion covering the key that is visible at the sequence number.
+    pub fn max_covering(&self, key: &Bytes, sequence: SequenceNumber) -> Option<SequenceNumber> {
+        let (_, fragment) = self.fragments.range(..=key.clone()).next_back()?;
+        if *key >= fragment.end {
+            return None;
+        }
+        let visible = fragment.sequences.partition_point(|s| *s <= sequence);
+        visible.checked_sub(1).map(|i| fragment.sequences[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::data::range_tombstone::RangeTombstones;
+
+    #[test]
+    fn overlapping_ranges_are_fragmented() {
+        let mut tombstones = RangeTombstones::new();
+        tombstones.add(Bytes::from("b"), Bytes::from("e"), 1);
+        tombstones.add(Bytes::from("a"), Bytes::from("c"), 2);
+        tombstones.add(Bytes::from("d"), Bytes::from("g"), 3);
+
+        assert_eq!(tombstones.max_covering(&Bytes::from("a"), 3), Some(2));
+        assert_eq!(tombstones.max_covering(&Bytes::from("b"), 3), Some(2));
+        assert_eq!(tombstones.max_covering(&Bytes::from("b"), 1), Some(1));
+        assert_eq!(tombstones.max_covering(&Bytes::from("c"), 3), Some(1));
+        assert_eq!(tombstones.max_covering(&Bytes::from("d"), 3), Some(3));
+        assert_eq!(tombstones.max_covering(&Bytes::from("d"), 2), Some(1));
+        assert_eq!(tombstones.max_covering(&Bytes::from("f"), 2), None);
+        assert_eq!(tombstones.max_covering(&Bytes::from("g"), 3), None);
+        assert_eq!(tombstones.max_covering(&Bytes::from("0"), 3), None);
+    }
+}
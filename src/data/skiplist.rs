@@ -1,26 +1,33 @@
-use std::cell::RefCell;
-use std::cmp::Ordering;
-use std::rc::Rc;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Ordering;
 
-use bytes::Bytes;
-use rand::random;
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
 
 const MAX_HEIGHT: usize = 12;
 const BRANCH_FACTOR: usize = 4;
+const RANDOM_SEED: u64 = 0xdeadbeef;
 
-pub struct SkipList {
-    head: Rc<RefCell<SkipListNode>>,
+type NodeRef<K, V> = Rc<RefCell<SkipListNode<K, V>>>;
+type Link<K, V> = Option<NodeRef<K, V>>;
+
+pub struct SkipList<K, V> {
+    head: NodeRef<K, V>,
     current_height: usize,
+    rng: SmallRng,
 }
 
-pub struct SkipListNode {
-    key: Bytes,
-    value: Bytes,
-    next: Vec<Option<Rc<RefCell<SkipListNode>>>>,
+pub struct SkipListNode<K, V> {
+    key: K,
+    value: V,
+    next: Vec<Link<K, V>>,
 }
 
-impl SkipListNode {
-    pub fn new(key: Bytes, value: Bytes) -> Self {
+impl<K, V> SkipListNode<K, V> {
+    pub fn new(key: K, value: V) -> Self {
         SkipListNode {
             key,
             value,
@@ -29,32 +36,45 @@ impl SkipListNode {
     }
 }
 
-impl SkipList {
+pub struct Iter<K, V> {
+    next: Link<K, V>,
+}
+
+impl<K: Clone, V: Clone> Iterator for Iter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next.take()?;
+        let node = node.borrow();
+        self.next = node.next[0].clone();
+        Some((node.key.clone(), node.value.clone()))
+    }
+}
+
+impl<K: Ord + Default + Clone, V: Default + Clone> Default for SkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Default + Clone, V: Default + Clone> SkipList<K, V> {
     pub fn new() -> Self {
-        let head = SkipListNode {
-            key: Bytes::new(),
-            value: Bytes::new(),
-            next: vec![None; MAX_HEIGHT],
-        };
+        let head = SkipListNode::new(K::default(), V::default());
         SkipList {
             head: Rc::new(RefCell::new(head)),
             current_height: 1,
+            rng: SmallRng::seed_from_u64(RANDOM_SEED),
         }
     }
 
-    fn less_than_eq(key: &Bytes, opt_node: &Option<Rc<RefCell<SkipListNode>>>) -> bool {
-        if opt_node.is_none() {
-            return true;
+    fn less_than_eq(key: &K, opt_node: &Link<K, V>) -> bool {
+        match opt_node {
+            None => true,
+            Some(node) => key.cmp(&node.borrow().key) != Ordering::Greater,
         }
-        let node = opt_node.as_ref().unwrap();
-        let order = key.cmp(&node.borrow().key);
-        return match order {
-            Ordering::Greater => false,
-            _ => true
-        };
     }
 
-    fn find_greater_or_eq(&self, key: &Bytes) -> (Option<Rc<RefCell<SkipListNode>>>, Vec<Rc<RefCell<SkipListNode>>>) {
+    fn find_greater_or_eq(&self, key: &K) -> (Link<K, V>, Vec<NodeRef<K, V>>) {
         let mut prev_nodes_by_levels = vec![self.head.clone(); MAX_HEIGHT];
         let mut level = self.current_height - 1;
         let mut current_node = self.head.clone();
@@ -72,10 +92,10 @@ impl SkipList {
         }
     }
 
-    fn calculate_random_height(&self) -> usize {
+    fn calculate_random_height(&mut self) -> usize {
         let mut height = 1;
         loop {
-            if height >= MAX_HEIGHT || random::<usize>() % BRANCH_FACTOR != 0 {
+            if height >= MAX_HEIGHT || !(self.rng.next_u32() as usize).is_multiple_of(BRANCH_FACTOR) {
                 break;
             }
             height += 1;
@@ -83,10 +103,12 @@ impl SkipList {
         height
     }
 
-    pub fn insert(&mut self, key: Bytes, value: Bytes) {
+    pub fn insert(&mut self, key: K, value: V) {
         let (found_next, mut prev_nodes_by_levels) = self.find_greater_or_eq(&key);
-        if found_next.is_some() && key.cmp(&found_next.as_ref().unwrap().borrow().key) == Ordering::Equal {
-            panic!("Same keys are not allowed here");
+        if let Some(node) = &found_next {
+            if key.cmp(&node.borrow().key) == Ordering::Equal {
+                panic!("Same keys are not allowed here");
+            }
         }
         let height = self.calculate_random_height();
         // Expand the scope of levels
@@ -107,30 +129,65 @@ impl SkipList {
         }
     }
 
-    pub fn contain(&self, key: &Bytes) -> bool {
+    pub fn contain(&self, key: &K) -> bool {
         let got = self.get(key);
         got.is_some()
     }
 
-    pub fn get(&self, key: &Bytes) -> Option<Bytes> {
+    pub fn get(&self, key: &K) -> Option<V> {
         let (found_next, _) = self.find_greater_or_eq(key);
-        if found_next.is_some() {
-            let node = &found_next.as_ref().unwrap().borrow();
+        if let Some(node) = &found_next {
+            let node = node.borrow();
             if key.cmp(&node.key) == Ordering::Equal {
                 return Some(node.value.clone());
             }
         }
         None
     }
+
+    pub fn get_less_than(&self, key: &K) -> Option<(K, V)> {
+        let (_, prev_nodes_by_levels) = self.find_greater_or_eq(key);
+        self.entry_of(&prev_nodes_by_levels[0])
+    }
+
+    pub fn last(&self) -> Option<(K, V)> {
+        let mut level = self.current_height - 1;
+        let mut current_node = self.head.clone();
+        loop {
+            let next_node_opt = current_node.borrow().next[level].clone();
+            match next_node_opt {
+                Some(next_node) => current_node = next_node,
+                None if level == 0 => return self.entry_of(&current_node),
+                None => level -= 1,
+            }
+        }
+    }
+
+    fn entry_of(&self, node: &NodeRef<K, V>) -> Option<(K, V)> {
+        if Rc::ptr_eq(node, &self.head) {
+            return None;
+        }
+        let node = node.borrow();
+        Some((node.key.clone(), node.value.clone()))
+    }
+
+    pub fn iter_from(&self, key: &K) -> Iter<K, V> {
+        let (found_next, _) = self.find_greater_or_eq(key);
+        Iter { next: found_next }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
     use bytes::Bytes;
 
     use crate::data::skiplist::SkipList;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn it_works() {
         let mut skip_list = SkipList::new();
         let key = Bytes::from("hello");
@@ -140,4 +197,27 @@ mod tests {
         assert_eq!(value.clone(), got.unwrap());
         assert_eq!(skip_list.contain(&key), true);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn iter_from() {
+        let mut skip_list = SkipList::new();
+        for key in ["d", "b", "a", "c"] {
+            skip_list.insert(Bytes::from(key), Bytes::new());
+        }
+        let keys: Vec<Bytes> = skip_list.iter_from(&Bytes::from("b")).map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![Bytes::from("b"), Bytes::from("c"), Bytes::from("d")]);
+    }
+
+    #[test]
+    fn get_less_than_and_last() {
+        let mut skip_list = SkipList::new();
+        assert!(skip_list.last().is_none());
+        for key in ["d", "b", "a", "c"] {
+            skip_list.insert(Bytes::from(key), Bytes::new());
+        }
+        assert_eq!(skip_list.get_less_than(&Bytes::from("c")).unwrap().0, Bytes::from("b"));
+        assert_eq!(skip_list.get_less_than(&Bytes::from("bb")).unwrap().0, Bytes::from("b"));
+        assert!(skip_list.get_less_than(&Bytes::from("a")).is_none());
+        assert_eq!(skip_list.last().unwrap().0, Bytes::from("d"));
+    }
+}
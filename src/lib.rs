@@ -1,6 +1,9 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 extern crate core;
 
-mod data;
+pub mod data;
 
 #[cfg(test)]
 mod tests {